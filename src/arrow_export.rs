@@ -0,0 +1,300 @@
+//! Columnar export of a joined `ShapeFile` + `DBF` feature set to an Arrow
+//! `RecordBatch`, gated behind the `arrow` feature so the dependency stays
+//! optional for callers who only need to read shapefiles. Every value is
+//! copied into the batch's arrays through Arrow's array builders.
+#![cfg(feature = "arrow")]
+
+extern crate arrow;
+
+use std::sync::Arc;
+
+use self::arrow::array::{ArrayRef, BooleanBuilder, Date32Builder, Float64Builder, ListBuilder,
+                          StringBuilder, StructBuilder};
+use self::arrow::datatypes::{DataType, Field, Schema};
+use self::arrow::record_batch::RecordBatch;
+
+use crate::dbf::{RecordField, DBF};
+use crate::error::ShapeError;
+use crate::shapefile::{Point, Shape, ShapeFile};
+
+enum ColumnBuilder {
+    Utf8(StringBuilder),
+    Float64(Float64Builder),
+    Boolean(BooleanBuilder),
+    Date32(Date32Builder),
+}
+
+fn arrow_type_for(field_type: u8) -> DataType {
+    match field_type {
+        b'C' | b'M' => DataType::Utf8,
+        b'N' | b'F' => DataType::Float64,
+        b'L' => DataType::Boolean,
+        b'D' => DataType::Date32,
+        //unreachable for a DBF we parsed ourselves -- FieldDescriptor::from_reader
+        //already rejects any other byte
+        _ => DataType::Utf8,
+    }
+}
+
+fn new_builder(field_type: u8, capacity: usize) -> ColumnBuilder {
+    match field_type {
+        b'C' | b'M' => ColumnBuilder::Utf8(StringBuilder::new(capacity)),
+        b'N' | b'F' => ColumnBuilder::Float64(Float64Builder::new(capacity)),
+        b'L' => ColumnBuilder::Boolean(BooleanBuilder::new(capacity)),
+        _ => ColumnBuilder::Date32(Date32Builder::new(capacity)),
+    }
+}
+
+//days since the Unix epoch, for Arrow's Date32 -- Howard Hinnant's well-known
+//`days_from_civil` algorithm, valid for the whole proleptic Gregorian calendar
+fn days_from_civil(year: i32, month: u32, day: u32) -> i32 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = (y - era * 400) as i64;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era as i64 * 146097 + doe - 719468) as i32
+}
+
+fn append_record_field(builder: &mut ColumnBuilder, field: RecordField) -> Result<(), ShapeError> {
+    match (builder, field) {
+        (&mut ColumnBuilder::Utf8(ref mut b), RecordField::Text(s)) => {
+            b.append_value(&s).map_err(arrow_io_err)?
+        }
+        (&mut ColumnBuilder::Float64(ref mut b), RecordField::Number(n)) => {
+            b.append_value(n).map_err(arrow_io_err)?
+        }
+        (&mut ColumnBuilder::Boolean(ref mut b), RecordField::Bool(v)) => {
+            b.append_value(v).map_err(arrow_io_err)?
+        }
+        (&mut ColumnBuilder::Date32(ref mut b), RecordField::Date(d)) => {
+            b.append_value(days_from_civil(d.year as i32, d.month as u32, d.day as u32))
+                .map_err(arrow_io_err)?
+        }
+        //the field type recorded in the FieldDescriptor and the RecordField variant
+        //field_by_index() produces for it always agree
+        _ => unreachable!("field type and decoded value disagree"),
+    }
+    Ok(())
+}
+
+fn finish(builder: ColumnBuilder) -> ArrayRef {
+    match builder {
+        ColumnBuilder::Utf8(mut b) => Arc::new(b.finish()),
+        ColumnBuilder::Float64(mut b) => Arc::new(b.finish()),
+        ColumnBuilder::Boolean(mut b) => Arc::new(b.finish()),
+        ColumnBuilder::Date32(mut b) => Arc::new(b.finish()),
+    }
+}
+
+fn arrow_io_err(e: arrow::error::ArrowError) -> ShapeError {
+    ShapeError::Io(::std::io::Error::new(::std::io::ErrorKind::Other, e.to_string()))
+}
+
+fn point_struct_fields() -> Vec<Field> {
+    vec![Field::new("x", DataType::Float64, false),
+         Field::new("y", DataType::Float64, false)]
+}
+
+fn new_geometry_builder(capacity: usize) -> ListBuilder<ListBuilder<StructBuilder>> {
+    let point_fields = point_struct_fields();
+    let point_builders: Vec<Box<dyn arrow::array::ArrayBuilder>> =
+        vec![Box::new(Float64Builder::new(capacity)), Box::new(Float64Builder::new(capacity))];
+    let point_builder = StructBuilder::new(point_fields, point_builders);
+    let ring_builder = ListBuilder::new(point_builder);
+    ListBuilder::new(ring_builder)
+}
+
+fn append_point(point_builder: &mut StructBuilder, p: &Point) -> Result<(), ShapeError> {
+    point_builder.field_builder::<Float64Builder>(0)
+        .expect("point struct field 0 is x")
+        .append_value(p.x)
+        .map_err(arrow_io_err)?;
+    point_builder.field_builder::<Float64Builder>(1)
+        .expect("point struct field 1 is y")
+        .append_value(p.y)
+        .map_err(arrow_io_err)?;
+    point_builder.append(true).map_err(arrow_io_err)
+}
+
+fn append_geometry(geometry_builder: &mut ListBuilder<ListBuilder<StructBuilder>>,
+                    shape: &Shape)
+                    -> Result<(), ShapeError> {
+    let points = shape.points();
+    for &(start, end) in shape.parts() {
+        let ring_builder = geometry_builder.values();
+        for p in &points[start..end] {
+            append_point(ring_builder.values(), p)?;
+        }
+        ring_builder.append(true).map_err(arrow_io_err)?;
+    }
+    geometry_builder.append(true).map_err(arrow_io_err)
+}
+
+/// Build a `RecordBatch` with one column per `FieldDescriptor` plus a
+/// `geometry` column of `List<List<Struct<x, y>>>` (rings within a feature).
+pub fn to_record_batch(shp: &ShapeFile, dbf: &DBF) -> Result<RecordBatch, ShapeError> {
+    let shapes = shp.shapes();
+    if shapes.len() != dbf.len() {
+        return Err(ShapeError::Io(::std::io::Error::new(::std::io::ErrorKind::InvalidData,
+                                                          format!("shapefile has {} shapes but DBF has {} records",
+                                                                  shapes.len(),
+                                                                  dbf.len()))));
+    }
+
+    let mut fields = Vec::with_capacity(dbf.num_fields() + 1);
+    let mut builders: Vec<ColumnBuilder> = dbf.fields()
+        .iter()
+        .map(|fd| {
+            fields.push(Field::new(&fd.name, arrow_type_for(fd.field_type), true));
+            new_builder(fd.field_type, shapes.len())
+        })
+        .collect();
+    fields.push(Field::new("geometry",
+                            DataType::List(Box::new(Field::new("item",
+                                                                 DataType::List(Box::new(Field::new("item",
+                                                                                                     DataType::Struct(point_struct_fields()),
+                                                                                                     true))),
+                                                                 true))),
+                            true));
+
+    let mut geometry_builder = new_geometry_builder(shapes.len());
+
+    for (shape, record) in shapes.iter().zip(dbf.iter_records()) {
+        for (i, builder) in builders.iter_mut().enumerate() {
+            append_record_field(builder, record.field_by_index(i)?)?;
+        }
+        append_geometry(&mut geometry_builder, shape)?;
+    }
+
+    let mut columns: Vec<ArrayRef> = builders.into_iter().map(finish).collect();
+    columns.push(Arc::new(geometry_builder.finish()));
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(arrow_io_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{BigEndian, ByteOrder, LittleEndian};
+    use crate::reader::FromReader;
+    use std::io::Cursor;
+
+    //a single Polygon shape: one ring, three points
+    fn triangle_shp_bytes() -> Vec<u8> {
+        let mut content = Vec::new();
+        let mut i32buf = [0u8; 4];
+        LittleEndian::write_i32(&mut i32buf, 5); //Polygon
+        content.extend_from_slice(&i32buf);
+        for v in &[0.0, 0.0, 10.0, 10.0] {
+            let mut f64buf = [0u8; 8];
+            LittleEndian::write_f64(&mut f64buf, *v);
+            content.extend_from_slice(&f64buf);
+        }
+        LittleEndian::write_i32(&mut i32buf, 1); //num_parts
+        content.extend_from_slice(&i32buf);
+        LittleEndian::write_i32(&mut i32buf, 3); //num_points
+        content.extend_from_slice(&i32buf);
+        content.extend_from_slice(&[0u8; 4]); //parts[0] start index
+        for &(x, y) in &[(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)] {
+            let mut f64buf = [0u8; 8];
+            LittleEndian::write_f64(&mut f64buf, x);
+            content.extend_from_slice(&f64buf);
+            LittleEndian::write_f64(&mut f64buf, y);
+            content.extend_from_slice(&f64buf);
+        }
+
+        let mut bytes = Vec::new();
+        let mut header = [0u8; 100];
+        BigEndian::write_u32(&mut header[0..4], 0x270a);
+        BigEndian::write_u32(&mut header[24..28], (100 + 8 + content.len()) as u32 / 2);
+        LittleEndian::write_i32(&mut header[28..32], 1000);
+        LittleEndian::write_i32(&mut header[32..36], 5); //Polygon
+        bytes.extend_from_slice(&header);
+
+        let mut record_number = [0u8; 4];
+        BigEndian::write_i32(&mut record_number, 1);
+        bytes.extend_from_slice(&record_number);
+        let mut content_length_words = [0u8; 4];
+        BigEndian::write_i32(&mut content_length_words, (content.len() / 2) as i32);
+        bytes.extend_from_slice(&content_length_words);
+        bytes.extend_from_slice(&content);
+        bytes
+    }
+
+    //a one-field (NAME/C), one-record .dbf if `with_record` is set, else empty --
+    //used to exercise the shape-count/record-count mismatch guard
+    fn name_dbf_bytes(with_record: bool) -> Vec<u8> {
+        let num_header_bytes: u16 = 32 + 32 + 1;
+        let bytes_per_record: u16 = 1 + 10;
+
+        let mut bytes = Vec::new();
+        let mut header = [0u8; 32];
+        header[0] = 0x03;
+        LittleEndian::write_u32(&mut header[4..8], if with_record { 1 } else { 0 });
+        LittleEndian::write_u16(&mut header[8..10], num_header_bytes);
+        LittleEndian::write_u16(&mut header[10..12], bytes_per_record);
+        bytes.extend_from_slice(&header);
+
+        let mut field = [0u8; 32];
+        field[..4].copy_from_slice(b"NAME");
+        field[11] = b'C';
+        field[16] = 10;
+        bytes.extend_from_slice(&field);
+
+        bytes.push(0x0D);
+        if with_record {
+            bytes.push(b' ');
+            bytes.extend_from_slice(b"Town      ");
+        }
+        bytes.push(0x1A);
+        bytes
+    }
+
+    #[test]
+    fn to_record_batch_dispatches_fields_and_nests_geometry() {
+        let shp = crate::shapefile::ShapeFile::from_reader(&mut Cursor::new(triangle_shp_bytes()),
+                                                             crate::reader::Endian::Little)
+            .unwrap();
+        let dbf = crate::dbf::DBF::from_reader(&mut Cursor::new(name_dbf_bytes(true)),
+                                                crate::reader::Endian::Little)
+            .unwrap();
+
+        let batch = to_record_batch(&shp, &dbf).unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.num_columns(), 2); //NAME + geometry
+
+        let name_column = batch.column(0)
+            .as_any()
+            .downcast_ref::<self::arrow::array::StringArray>()
+            .unwrap();
+        assert_eq!(name_column.value(0), "Town");
+
+        let features = batch.column(1)
+            .as_any()
+            .downcast_ref::<self::arrow::array::ListArray>()
+            .unwrap();
+        assert_eq!(features.len(), 1); //one feature
+        let rings = features.value(0);
+        let rings = rings.as_any().downcast_ref::<self::arrow::array::ListArray>().unwrap();
+        assert_eq!(rings.len(), 1); //one ring
+        let points = rings.value(0);
+        assert_eq!(points.len(), 3); //three points in the ring
+    }
+
+    #[test]
+    fn to_record_batch_rejects_shape_dbf_count_mismatch() {
+        let shp = crate::shapefile::ShapeFile::from_reader(&mut Cursor::new(triangle_shp_bytes()),
+                                                             crate::reader::Endian::Little)
+            .unwrap();
+        let dbf = crate::dbf::DBF::from_reader(&mut Cursor::new(name_dbf_bytes(false)),
+                                                crate::reader::Endian::Little)
+            .unwrap();
+
+        match to_record_batch(&shp, &dbf) {
+            Err(ShapeError::Io(e)) => assert_eq!(e.kind(), ::std::io::ErrorKind::InvalidData),
+            other => panic!("expected an InvalidData io error, got {:?}", other),
+        }
+    }
+}
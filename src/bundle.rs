@@ -0,0 +1,145 @@
+//! Shapefiles are almost always distributed as a `.zip` holding the `.shp`,
+//! `.shx`, `.dbf`, and `.prj` together. This module lets the `.shp`/`.shx`/`.dbf`
+//! readers pull their member straight out of such an archive instead of
+//! requiring the caller to extract it to disk first.
+
+extern crate zip;
+
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek};
+
+use self::zip::ZipArchive;
+
+use crate::dbf::DBF;
+use crate::error::{DbfError, ShapeError};
+use crate::reader::{Endian, FromReader};
+use crate::shapefile::ShapeFile;
+use crate::shx::{IndexedShapeFile, ShapeIndex};
+
+//a decompressed zip member, buffered into memory so it can be `Seek`'d --
+//`zip`'s own per-entry reader only supports `Read`, which the .shx random-access
+//path can't work with
+fn read_member<R: Read + Seek>(archive: &mut ZipArchive<R>, suffix: &str) -> io::Result<Vec<u8>> {
+    let lower_suffix = suffix.to_lowercase();
+    let name = archive.file_names()
+        .find(|name| name.to_lowercase().ends_with(&lower_suffix))
+        .map(String::from)
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound,
+                            format!("no {} member found in zip archive", suffix))
+        })?;
+
+    let mut member = archive.by_name(&name).map_err(io::Error::from)?;
+    let mut buffer = Vec::with_capacity(member.size() as usize);
+    member.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+impl ShapeFile {
+    pub fn from_zip(path: &str) -> Result<Self, ShapeError> {
+        Self::from_zip_reader(File::open(path)?)
+    }
+
+    pub fn from_zip_reader<R: Read + Seek>(reader: R) -> Result<Self, ShapeError> {
+        let mut archive = ZipArchive::new(reader).map_err(io::Error::from)?;
+        let shp_bytes = read_member(&mut archive, ".shp")?;
+        let mut cursor = Cursor::new(shp_bytes);
+        ShapeFile::from_reader(&mut cursor, Endian::Little)
+    }
+}
+
+impl DBF {
+    pub fn from_zip(path: &str) -> Result<Self, DbfError> {
+        Self::from_zip_reader(File::open(path)?)
+    }
+
+    pub fn from_zip_reader<R: Read + Seek>(reader: R) -> Result<Self, DbfError> {
+        let mut archive = ZipArchive::new(reader).map_err(io::Error::from)?;
+        let dbf_bytes = read_member(&mut archive, ".dbf")?;
+        let mut cursor = Cursor::new(dbf_bytes);
+        DBF::from_reader(&mut cursor, Endian::Little)
+    }
+}
+
+impl IndexedShapeFile<Cursor<Vec<u8>>> {
+    pub fn from_zip(path: &str) -> Result<Self, ShapeError> {
+        Self::from_zip_reader(File::open(path)?)
+    }
+
+    pub fn from_zip_reader<R: Read + Seek>(reader: R) -> Result<Self, ShapeError> {
+        let mut archive = ZipArchive::new(reader).map_err(io::Error::from)?;
+        let shx_bytes = read_member(&mut archive, ".shx")?;
+        let shp_bytes = read_member(&mut archive, ".shp")?;
+
+        let index = ShapeIndex::from_reader(&mut Cursor::new(shx_bytes), Endian::Big)?;
+        Ok(IndexedShapeFile::new(Cursor::new(shp_bytes), index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::zip::write::{FileOptions, ZipWriter};
+    use byteorder::{BigEndian as BE, ByteOrder, LittleEndian as LE};
+    use std::io::Write;
+
+    //a .shp with no shapes -- just enough to prove from_zip_reader actually
+    //extracted the right member and handed it to ShapeFile::from_reader
+    fn empty_shp_bytes() -> Vec<u8> {
+        let mut header = [0u8; 100];
+        BE::write_u32(&mut header[0..4], 0x270a);
+        BE::write_u32(&mut header[24..28], 50); //100 bytes / 2
+        LE::write_i32(&mut header[28..32], 1000);
+        LE::write_i32(&mut header[32..36], 0); //Null
+        header.to_vec()
+    }
+
+    //a one-field, one-record .dbf, same layout as dbf.rs's own fixture
+    fn one_record_dbf_bytes() -> Vec<u8> {
+        let num_header_bytes: u16 = 32 + 32 + 1;
+        let bytes_per_record: u16 = 1 + 10;
+
+        let mut bytes = Vec::new();
+        let mut header = [0u8; 32];
+        header[0] = 0x03;
+        LE::write_u32(&mut header[4..8], 1);
+        LE::write_u16(&mut header[8..10], num_header_bytes);
+        LE::write_u16(&mut header[10..12], bytes_per_record);
+        bytes.extend_from_slice(&header);
+
+        let mut field = [0u8; 32];
+        field[..4].copy_from_slice(b"NAME");
+        field[11] = b'C';
+        field[16] = 10;
+        bytes.extend_from_slice(&field);
+
+        bytes.push(0x0D);
+        bytes.push(b' ');
+        bytes.extend_from_slice(b"Colorado  ");
+        bytes.push(0x1A);
+        bytes
+    }
+
+    fn zip_bundle(shp: &[u8], dbf: &[u8]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+        writer.start_file("bundle.shp", options).unwrap();
+        writer.write_all(shp).unwrap();
+        writer.start_file("bundle.dbf", options).unwrap();
+        writer.write_all(dbf).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn from_zip_reader_extracts_shp_and_dbf_members() {
+        let bundle = zip_bundle(&empty_shp_bytes(), &one_record_dbf_bytes());
+
+        let shapefile = ShapeFile::from_zip_reader(Cursor::new(bundle.clone())).unwrap();
+        assert_eq!(shapefile.shapes().len(), 0);
+
+        let dbf = DBF::from_zip_reader(Cursor::new(bundle)).unwrap();
+        assert_eq!(dbf.len(), 1);
+        assert_eq!(dbf[0].field_by_name("NAME").unwrap().unwrap(),
+                   crate::dbf::RecordField::Text(String::from("Colorado")));
+    }
+}
@@ -1,10 +1,14 @@
 
 
-use std::error::Error;
 use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::ops::Index;
 use std::rc::Rc;
 
+use crate::error::DbfError;
+use crate::reader::{Endian, FromReader};
+use crate::writer::ToWriter;
+
 
 pub struct DBF {
     last_modified: Date,
@@ -20,6 +24,39 @@ pub struct FieldDescriptor {
     pub field_start: u16,
 }
 
+impl FromReader for FieldDescriptor {
+    type Error = DbfError;
+
+    //`field_start` is meaningless for a single descriptor read in isolation; the
+    //caller fills it in once it knows the running byte offset across all fields.
+    fn from_reader<R: Read + Seek>(r: &mut R, _endian: Endian) -> Result<Self, DbfError> {
+        let offset = r.stream_position()?;
+
+        let mut buf = [0u8; 32];
+        r.read_exact(&mut buf)?;
+
+        let field_name = unsafe { str_from_u8_nul_utf8(&buf[..11]) };
+        let field_type = buf[11];
+        let field_length = buf[16];
+        match field_type {
+            b'C' | b'D' | b'F' | b'L' | b'M' | b'N' => (),
+            _ => {
+                return Err(DbfError::InvalidFieldType {
+                    offset: offset + 11,
+                    value: field_type,
+                })
+            }
+        }
+
+        Ok(FieldDescriptor {
+            name: String::from(field_name),
+            field_type: field_type,
+            field_length: field_length,
+            field_start: 0,
+        })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Date {
     pub year: u32,
@@ -32,6 +69,32 @@ pub struct Record {
     fields: Rc<Vec<FieldDescriptor>>,
 }
 
+impl Record {
+    //doesn't implement `FromReader` because a record can't be parsed without
+    //knowing the field layout and record width the containing DBF has already read
+    //
+    //`bytes_per_record` (from the DBF header) counts the leading deletion-flag
+    //byte that precedes every record on disk; `data` holds only the real field
+    //bytes, so the flag is read and discarded here rather than carried along as
+    //if it were part of the record (carrying it made the last record swallow the
+    //file's trailing EOF marker instead of the next record's flag, since there
+    //is no next record to supply one)
+    fn from_reader<R: Read + Seek>(r: &mut R,
+                                    fields: Rc<Vec<FieldDescriptor>>,
+                                    bytes_per_record: u16)
+                                    -> Result<Self, DbfError> {
+        let mut flag = [0u8; 1];
+        r.read_exact(&mut flag)?;
+
+        let mut data = vec![0u8; bytes_per_record as usize - 1];
+        r.read_exact(&mut data)?;
+        Ok(Record {
+            data: data,
+            fields: fields,
+        })
+    }
+}
+
 
 #[derive(Debug, PartialEq)]
 pub enum RecordField {
@@ -42,21 +105,15 @@ pub enum RecordField {
 }
 
 
-impl DBF {
-    pub fn from_file(filename: &str) -> Result<Self, Box<Error>> {
-        use std::fs::File;
-        use std::io::prelude::*;
-        use std::io::SeekFrom;
-        use std::mem;
+impl FromReader for DBF {
+    type Error = DbfError;
 
-        let mut f = File::open(filename)?;
-        //we're just reading into it, so leave it uninitialized
-        let mut header_start: [u8; 32] = unsafe { mem::uninitialized() };
-        f.read_exact(&mut header_start)?;
+    fn from_reader<R: Read + Seek>(r: &mut R, endian: Endian) -> Result<Self, DbfError> {
+        let mut header_start = [0u8; 32];
+        r.read_exact(&mut header_start)?;
 
         let date = parse_date_binary(&header_start[1..4]);
 
-
         let num_records = LittleEndian::read_u32(&header_start[4..8]);
         let num_header_bytes = LittleEndian::read_u16(&header_start[8..10]);
         let bytes_per_record = LittleEndian::read_u16(&header_start[10..12]);
@@ -66,50 +123,34 @@ impl DBF {
         let mut fields = Vec::with_capacity(num_fields as usize);
         let mut field_byte_offset: u16 = 0;
         for _ in 0..num_fields {
-            //don't initialize if we're just going to read into it
-            let mut fd_buffer: [u8; 32] = unsafe { mem::uninitialized() };
-            f.read_exact(&mut fd_buffer)?;
-
-            let field_name = unsafe { str_from_u8_nul_utf8(&fd_buffer[..11]) };
-            let field_length = fd_buffer[16];
-            let field_type = fd_buffer[11];
-            match field_type {
-                b'C' | b'D' | b'F' | b'L' | b'M' | b'N' => (),
-                _ => return Err(From::from("invalid field type")),
-            }
-
-            fields.push(FieldDescriptor {
-                            name: String::from(field_name),
-                            field_type: field_type,
-                            field_length: field_length,
-                            field_start: field_byte_offset,
-                        });
-            field_byte_offset += field_length as u16;
+            let mut field = FieldDescriptor::from_reader(r, endian)?;
+            field.field_start = field_byte_offset;
+            field_byte_offset += field.field_length as u16;
+            fields.push(field);
         }
-        let records = Vec::with_capacity(num_records as usize);
+        let fields = Rc::new(fields);
 
+        //seek to the start of the records, right after the header terminator
+        r.seek(SeekFrom::Start(num_header_bytes as u64))?;
+        let mut records = Vec::with_capacity(num_records as usize);
+        for _ in 0..num_records {
+            records.push(Record::from_reader(r, fields.clone(), bytes_per_record)?);
+        }
 
-        let mut dbf = DBF {
+        Ok(DBF {
             last_modified: date,
-            fields: Rc::new(fields),
+            fields: fields,
             records: records,
-        };
-        //seek to the start of the records
-        f.seek(SeekFrom::Start(num_header_bytes as u64 + 1))?;
-        for _ in 0..num_records {
-            //create uninitialized buffer
-            let mut record_buf = Vec::with_capacity(bytes_per_record as usize);
-            unsafe { record_buf.set_len(bytes_per_record as usize) };
-            f.read_exact(&mut record_buf)?;
-            dbf.records
-                .push(Record {
-                          data: record_buf,
-                          fields: dbf.fields.clone(),
-                      });
-        }
+        })
+    }
+}
 
+impl DBF {
+    pub fn from_file(filename: &str) -> Result<Self, DbfError> {
+        use std::fs::File;
 
-        Ok(dbf)
+        let mut f = File::open(filename)?;
+        Self::from_reader(&mut f, Endian::Little)
     }
 
     pub fn last_modified(&self) -> &Date {
@@ -124,6 +165,14 @@ impl DBF {
         self.fields.len()
     }
 
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
     pub fn iter_records(&self) -> RecordsIterator {
         RecordsIterator {
             parent: self,
@@ -180,50 +229,72 @@ fn parse_date_binary(buffer: &[u8]) -> Date {
     }
 }
 
-fn parse_date_text(buffer: &[u8]) -> Date {
+fn parse_date_text(buffer: &[u8], field_name: &str, record_offset: u64) -> Result<Date, DbfError> {
     use std::str;
-    Date {
-        year: str::from_utf8(&buffer[..4]).unwrap().parse().unwrap(),
-        month: str::from_utf8(&buffer[4..6]).unwrap().parse().unwrap(),
-        day: str::from_utf8(&buffer[6..8]).unwrap().parse().unwrap(),
+
+    let malformed = || {
+        DbfError::MalformedDate {
+            field: String::from(field_name),
+            offset: record_offset,
+        }
+    };
+
+    if buffer.len() < 8 {
+        return Err(malformed());
     }
+    let text = str::from_utf8(buffer).map_err(|_| malformed())?;
+    Ok(Date {
+        year: text[..4].parse().map_err(|_| malformed())?,
+        month: text[4..6].parse().map_err(|_| malformed())?,
+        day: text[6..8].parse().map_err(|_| malformed())?,
+    })
 }
 
 impl Record {
-    pub fn field_by_index(&self, index: usize) -> RecordField {
-        use std::str;
+    pub fn field_by_index(&self, index: usize) -> Result<RecordField, DbfError> {
         let ref fields = self.fields;
         let start = fields[index].field_start as usize;
         let end = start + fields[index].field_length as usize;
 
         let field_slice = &self.data[start..end];
 
-        match fields[index].field_type {
+        let field = match fields[index].field_type {
             b'C' | b'M' => {
                 unsafe {
                     RecordField::Text(String::from(str_from_u8_ws_padded(field_slice).trim()))
                 }
             }
 
-            b'D' => RecordField::Date(parse_date_text(field_slice)),
-            b'F' | b'N' => unsafe {
-                RecordField::Number(str_from_u8_ws_padded(field_slice)
-                                        .trim()
-                                        .parse()
-                                        .unwrap())
-
-            },
+            b'D' => {
+                RecordField::Date(parse_date_text(field_slice, &fields[index].name, start as u64)?)
+            }
+            b'F' | b'N' => {
+                let text = unsafe { str_from_u8_ws_padded(field_slice) }.trim();
+                let value = text.parse().map_err(|_| {
+                    DbfError::MalformedNumber {
+                        field: fields[index].name.clone(),
+                        offset: start as u64,
+                    }
+                })?;
+                RecordField::Number(value)
+            }
             b'L' => {
                 RecordField::Bool(field_slice[0] == b'Y' || field_slice[0] == b'y' ||
                                   field_slice[0] == b'T' ||
                                   field_slice[0] == b't')
             }
-            _ => panic!(),
+            other => {
+                return Err(DbfError::InvalidFieldType {
+                    offset: start as u64,
+                    value: other,
+                })
+            }
 
-        }
+        };
+        Ok(field)
     }
 
-    pub fn field_by_name(&self, field_name: &str) -> Option<RecordField> {
+    pub fn field_by_name(&self, field_name: &str) -> Option<Result<RecordField, DbfError>> {
         let field_index = self.fields
             .iter()
             .position(|ref s| s.name == field_name);
@@ -232,9 +303,117 @@ impl Record {
 }
 
 
+impl ToWriter for FieldDescriptor {
+    type Error = DbfError;
+
+    fn to_writer<W: Write>(&self, w: &mut W, _endian: Endian) -> Result<(), DbfError> {
+        let mut buf = [0u8; 32];
+        let name_bytes = self.name.as_bytes();
+        let name_len = name_bytes.len().min(11);
+        buf[..name_len].copy_from_slice(&name_bytes[..name_len]);
+        buf[11] = self.field_type;
+        buf[16] = self.field_length;
+        w.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+impl ToWriter for DBF {
+    type Error = DbfError;
+
+    fn to_writer<W: Write>(&self, w: &mut W, endian: Endian) -> Result<(), DbfError> {
+        //every record was read with the same width, so any one of them tells us the
+        //on-disk field width; +1 accounts for the leading deletion-flag byte that
+        //precedes each record on disk but isn't stored in `Record::data`
+        let bytes_per_record = self.records
+            .get(0)
+            .map(|r| r.data.len())
+            .unwrap_or_else(|| self.fields.iter().map(|f| f.field_length as usize).sum()) as u16 +
+                              1;
+        let num_header_bytes = 32 + 32 * self.fields.len() as u16 + 1;
+
+        let mut header = [0u8; 32];
+        header[0] = 0x03; //dBASE III PLUS, no memo
+        header[1] = (self.last_modified.year - 1900) as u8;
+        header[2] = self.last_modified.month;
+        header[3] = self.last_modified.day;
+        LittleEndian::write_u32(&mut header[4..8], self.records.len() as u32);
+        LittleEndian::write_u16(&mut header[8..10], num_header_bytes);
+        LittleEndian::write_u16(&mut header[10..12], bytes_per_record);
+        w.write_all(&header)?;
+
+        for field in self.fields.iter() {
+            field.to_writer(w, endian)?;
+        }
+        w.write_all(&[0x0D])?; //header terminator
+
+        for record in self.records.iter() {
+            w.write_all(&[b' '])?; //deletion flag -- this crate never marks records deleted
+            w.write_all(&record.data)?;
+        }
+        w.write_all(&[0x1A])?; //EOF marker
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
+
+    //hand-built single-field, two-record .dbf, entirely in memory so the
+    //round-trip test below needs no file fixture
+    fn fixture_bytes() -> Vec<u8> {
+        let num_header_bytes: u16 = 32 + 32 + 1; //one field descriptor
+        let bytes_per_record: u16 = 1 + 10; //deletion flag + a 10-byte "NAME" field
+
+        let mut bytes = vec![0u8; 0];
+
+        let mut header = [0u8; 32];
+        header[0] = 0x03;
+        header[1] = 2016 - 1900;
+        header[2] = 2;
+        header[3] = 17;
+        LittleEndian::write_u32(&mut header[4..8], 2);
+        LittleEndian::write_u16(&mut header[8..10], num_header_bytes);
+        LittleEndian::write_u16(&mut header[10..12], bytes_per_record);
+        bytes.extend_from_slice(&header);
+
+        let mut field = [0u8; 32];
+        field[..4].copy_from_slice(b"NAME");
+        field[11] = b'C';
+        field[16] = 10;
+        bytes.extend_from_slice(&field);
+
+        bytes.push(0x0D); //header terminator
+
+        bytes.push(b' '); //deletion flag
+        bytes.extend_from_slice(b"Colorado  ");
+        bytes.push(b' '); //deletion flag
+        bytes.extend_from_slice(b"Texas     ");
+
+        bytes.push(0x1A); //EOF marker
+        bytes
+    }
+
+    #[test]
+    fn to_writer_round_trip() {
+        let original = fixture_bytes();
+        let dbf = DBF::from_reader(&mut Cursor::new(original.clone()), Endian::Little).unwrap();
+
+        let mut written = Vec::new();
+        dbf.to_writer(&mut written, Endian::Little).unwrap();
+        assert_eq!(written, original,
+                   "re-serializing an unmodified DBF must reproduce the exact original bytes");
+
+        let reparsed = DBF::from_reader(&mut Cursor::new(written), Endian::Little).unwrap();
+        assert_eq!(reparsed[0].field_by_name("NAME").unwrap().unwrap(),
+                   RecordField::Text(String::from("Colorado")));
+        assert_eq!(reparsed[1].field_by_name("NAME").unwrap().unwrap(),
+                   RecordField::Text(String::from("Texas")));
+    }
+
     #[test]
     fn dbf_test() {
         let dbf = DBF::from_file("test_inputs/test_dbf.dbf").unwrap();
@@ -250,12 +429,12 @@ mod tests {
         assert_eq!(dbf.fields[0].name, "STATEFP");
         assert_eq!(dbf.fields[0].field_type, b'C');
 
-        assert_eq!(dbf[25].field_by_name("NAME").unwrap(),
+        assert_eq!(dbf[25].field_by_name("NAME").unwrap().unwrap(),
                    RecordField::Text(String::from("Colorado")));
 
         for rec in dbf.iter_records().take(5) {
             for i in 0..dbf.num_fields() {
-                println!("field number {} : {:?}", i, rec.field_by_index(i));
+                println!("field number {} : {:?}", i, rec.field_by_index(i).unwrap());
             }
         }
 
@@ -0,0 +1,49 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::shapefile::ShapeType;
+
+/// Errors produced while parsing or writing a `.shp`/`.shx` shapefile.
+#[derive(Debug, Error)]
+pub enum ShapeError {
+    #[error("i/o error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid .shp file, magic number is {0:02x?}")]
+    BadMagic([u8; 4]),
+
+    #[error("file length field says {header} bytes but file is {actual} bytes")]
+    FileLengthMismatch { header: u64, actual: u64 },
+
+    #[error("invalid shape type {value} at offset 0x{offset:X}")]
+    InvalidShapeType { offset: u64, value: i32 },
+
+    #[error("bad record length at offset 0x{offset:X}")]
+    BadRecordLength { offset: u64 },
+
+    #[error("shape type {0:?} is not implemented yet")]
+    UnsupportedShapeType(ShapeType),
+
+    #[error("shape index {index} is out of range, .shx has {len} entries")]
+    IndexOutOfRange { index: usize, len: usize },
+
+    #[error(transparent)]
+    Dbf(#[from] DbfError),
+}
+
+/// Errors produced while parsing or writing a `.dbf` attribute table.
+#[derive(Debug, Error)]
+pub enum DbfError {
+    #[error("i/o error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid field type {value:?} at offset 0x{offset:X}")]
+    InvalidFieldType { offset: u64, value: u8 },
+
+    #[error("malformed number in field {field:?} at record-relative offset 0x{offset:X}")]
+    MalformedNumber { field: String, offset: u64 },
+
+    #[error("malformed date in field {field:?} at record-relative offset 0x{offset:X}")]
+    MalformedDate { field: String, offset: u64 },
+}
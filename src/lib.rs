@@ -0,0 +1,13 @@
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+pub mod bundle;
+pub mod dbf;
+pub mod error;
+pub mod reader;
+pub mod shapefile;
+pub mod shx;
+pub mod writer;
+
+pub use error::{DbfError, ShapeError};
+pub use reader::{Endian, FromReader};
+pub use writer::ToWriter;
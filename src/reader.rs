@@ -0,0 +1,24 @@
+use std::io::{Read, Seek};
+
+/// Byte order of the data being read. Shapefiles and DBF files are not
+/// internally consistent about this (the .shp header mixes big- and
+/// little-endian fields), so individual `FromReader` impls are free to
+/// ignore this when the on-disk format mandates a specific order, and only
+/// consult it where the format genuinely varies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// Parse `Self` out of any `Read + Seek`, not just a `File` opened by path.
+///
+/// This lets callers hand in a `Cursor<Vec<u8>>`, a network stream, or an
+/// entry pulled out of an archive, and is the basis for `from_file` helpers
+/// throughout the crate, which are thin wrappers that open a `File` and
+/// delegate here.
+pub trait FromReader: Sized {
+    type Error;
+
+    fn from_reader<R: Read + Seek>(r: &mut R, endian: Endian) -> Result<Self, Self::Error>;
+}
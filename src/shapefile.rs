@@ -1,9 +1,13 @@
 
 extern crate byteorder;
 use self::byteorder::{ByteOrder, LittleEndian, BigEndian};
-use std::error::Error;
 use enum_primitive::FromPrimitive;
 use std::f64;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::error::ShapeError;
+use crate::reader::{Endian, FromReader};
+use crate::writer::ToWriter;
 
 
 
@@ -17,8 +21,13 @@ pub struct ShapeFile {
 pub struct Point {
     pub x: f64,
     pub y: f64,
+    pub z: Option<f64>,
+    pub m: Option<f64>,
 }
 
+//any M value at or below this is a NoData sentinel per the shapefile spec
+const NO_DATA: f64 = -1.0e38;
+
 pub struct BoundingBox {
     pub min: Point,
     pub max: Point,
@@ -27,7 +36,7 @@ pub struct BoundingBox {
 
 
 enum_from_primitive! {
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, PartialEq, Copy, Clone)]
     pub enum ShapeType{
     Null = 0,
     Point = 1,
@@ -46,76 +55,111 @@ enum_from_primitive! {
 }
 }
 
-//Works for all the 2D shape types
 pub struct Shape {
     shape_type: ShapeType,
     bounding_box: BoundingBox,
     points: Vec<Point>,
     parts: Vec<(usize, usize)>,
-    //todo Z and M stuff
 }
 
-impl ShapeFile {
-    pub fn from_file(filename: &str) -> Result<Self, Box<Error>> {
-        use std::fs;
-        use std::fs::File;
-        use std::io::prelude::*;
-        use std::io::SeekFrom;
-        use std::mem;
+impl Shape {
+    pub fn shape_type(&self) -> ShapeType {
+        self.shape_type
+    }
 
-        let mut f = File::open(filename)?;
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    //each `(start, end)` indexes a contiguous run of `points()` making up one ring/part
+    pub fn parts(&self) -> &[(usize, usize)] {
+        &self.parts
+    }
+}
 
-        let mut header: [u8; 100] = unsafe { mem::uninitialized() };
-        f.read_exact(&mut header)?;
+impl FromReader for ShapeFile {
+    type Error = ShapeError;
 
-        if BigEndian::read_u32(&header[..4]) != 0x270a {
-            return Err(From::from("invalid .shp file, magic number is wrong"));
-        }
+    fn from_reader<R: Read + Seek>(r: &mut R, endian: Endian) -> Result<Self, ShapeError> {
+        let mut header = [0u8; 100];
+        r.read_exact(&mut header)?;
 
-        let file_length = BigEndian::read_u32(&header[24..28]) * 2;
-        let metadata = fs::metadata(filename).unwrap();
-        if metadata.len() != file_length as u64 {
-            return Err(From::from("file length field doesn't match header"));
+        let magic = BigEndian::read_u32(&header[..4]);
+        if magic != 0x270a {
+            let mut magic_bytes = [0u8; 4];
+            magic_bytes.copy_from_slice(&header[..4]);
+            return Err(ShapeError::BadMagic(magic_bytes));
         }
 
-        let shape_type = ShapeType::from_i32(LittleEndian::read_i32(&header[32..36]));
-        if let None = shape_type {
-            return Err(From::from("invalid shape type"));
+        let file_length = BigEndian::read_u32(&header[24..28]) as u64 * 2;
+        let total_length = r.seek(SeekFrom::End(0))?;
+        if total_length != file_length {
+            return Err(ShapeError::FileLengthMismatch {
+                header: file_length,
+                actual: total_length,
+            });
         }
-        let shape_type = shape_type.unwrap();
+        r.seek(SeekFrom::Start(100))?;
+
+        let raw_shape_type = LittleEndian::read_i32(&header[32..36]);
+        let shape_type = ShapeType::from_i32(raw_shape_type).ok_or(ShapeError::InvalidShapeType {
+            offset: 32,
+            value: raw_shape_type,
+        })?;
 
         let bounding_box = BoundingBox::from_bytes(&header[36..68]);
 
-        let mut buffer = Vec::new();
-        f.read_to_end(&mut buffer)?;
-        let mut shapes  = Vec::new();
-        let mut buffer_slice = buffer.as_slice();
-        while !buffer_slice.is_empty() {
-            let shape = Shape::from_bytes(&mut buffer_slice)?;
-            shapes.push(shape);
+        let mut shapes = Vec::new();
+        while r.stream_position()? < total_length {
+            shapes.push(Shape::from_reader(r, endian)?);
         }
+
         Ok(ShapeFile{
             bounding_box: bounding_box,
             shape_type: shape_type,
             shapes: shapes,
-            
         })
     }
 }
 
+impl ShapeFile {
+    pub fn from_file(filename: &str) -> Result<Self, ShapeError> {
+        use std::fs::File;
+
+        let mut f = File::open(filename)?;
+        Self::from_reader(&mut f, Endian::Little)
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+}
+
 impl Point {
     pub fn new(x: f64, y: f64) -> Self {
-        Self { x: x, y: y }
+        Self { x: x, y: y, z: None, m: None }
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Self {
         Self {
             x: LittleEndian::read_f64(&bytes[0..8]),
             y: LittleEndian::read_f64(&bytes[8..16]),
+            z: None,
+            m: None,
         }
     }
 }
 
+impl FromReader for Point {
+    type Error = ShapeError;
+
+    fn from_reader<R: Read + Seek>(r: &mut R, _endian: Endian) -> Result<Self, ShapeError> {
+        let mut bytes = [0u8; 16];
+        r.read_exact(&mut bytes)?;
+        Ok(Point::from_bytes(&bytes))
+    }
+}
+
 impl BoundingBox {
     pub fn from_bytes(bytes: &[u8]) -> Self {
         Self {
@@ -138,6 +182,16 @@ impl BoundingBox {
     }
 }
 
+impl FromReader for BoundingBox {
+    type Error = ShapeError;
+
+    fn from_reader<R: Read + Seek>(r: &mut R, _endian: Endian) -> Result<Self, ShapeError> {
+        let mut bytes = [0u8; 32];
+        r.read_exact(&mut bytes)?;
+        Ok(BoundingBox::from_bytes(&bytes))
+    }
+}
+
 fn read_points(bytes: &[u8], num_points: usize) -> Vec<Point> {
 
     let mut points = Vec::with_capacity(num_points as usize);
@@ -149,6 +203,18 @@ fn read_points(bytes: &[u8], num_points: usize) -> Vec<Point> {
     points
 }
 
+fn read_f64_array(bytes: &[u8], count: usize) -> Vec<f64> {
+    (0..count).map(|i| LittleEndian::read_f64(&bytes[(8 * i)..(8 * i + 8)])).collect()
+}
+
+//M arrays (unlike Z arrays) may contain the NoData sentinel in place of a real value
+fn read_m_array(bytes: &[u8], count: usize) -> Vec<Option<f64>> {
+    read_f64_array(bytes, count)
+        .into_iter()
+        .map(|m| if m <= NO_DATA { None } else { Some(m) })
+        .collect()
+}
+
 fn read_parts(bytes: &[u8], num_parts: usize, num_points: usize) -> Vec<(usize, usize)> {
     let mut parts: Vec<(usize, usize)> = Vec::with_capacity(num_parts);
     for i in 0..(num_parts - 1) {
@@ -165,16 +231,27 @@ fn read_parts(bytes: &[u8], num_parts: usize, num_points: usize) -> Vec<(usize,
     parts
 }
 
-impl Shape {
-    //mutable because we'll cut chop off this shape's bytes before returning
-    pub fn from_bytes(bytes:  &mut &[u8]) -> Result<Self, Box<Error>> {
+impl FromReader for Shape {
+    type Error = ShapeError;
+
+    fn from_reader<R: Read + Seek>(r: &mut R, _endian: Endian) -> Result<Self, ShapeError> {
+        let offset = r.stream_position()?;
 
-        let record_length = 2 * BigEndian::read_i32(&bytes[4..8]);
-        let shape_type = ShapeType::from_i32(LittleEndian::read_i32(&bytes[8..12]));
-        if shape_type.is_none() {
-            return Err(From::from("invalid shape type"));
+        let mut record_header = [0u8; 8];
+        r.read_exact(&mut record_header)?;
+        let record_length = 2 * BigEndian::read_i32(&record_header[4..8]);
+        if record_length < 0 {
+            return Err(ShapeError::BadRecordLength { offset: offset });
         }
-        let shape_type: ShapeType = shape_type.unwrap();
+
+        let mut content = vec![0u8; record_length as usize];
+        r.read_exact(&mut content)?;
+
+        let raw_shape_type = LittleEndian::read_i32(&content[0..4]);
+        let shape_type = ShapeType::from_i32(raw_shape_type).ok_or(ShapeError::InvalidShapeType {
+            offset: offset + 8,
+            value: raw_shape_type,
+        })?;
 
         let bb: BoundingBox;
         let points: Vec<Point>;
@@ -187,31 +264,135 @@ impl Shape {
                 parts = vec![];
             }
             ShapeType::Point => {
-                let p = Point::from_bytes(&bytes[12..28]);
+                let p = Point::from_bytes(&content[4..20]);
                 bb = BoundingBox::from_point(p);
                 points = vec![p];
                 parts = vec![(0, 1)];
             }
             ShapeType::MultiPoint => {
-                bb = BoundingBox::from_bytes(&bytes[12..44]);
-                let num_points = LittleEndian::read_i32(&bytes[44..48]) as usize;
-                points = read_points(&bytes[48..], num_points);
+                bb = BoundingBox::from_bytes(&content[4..36]);
+                let num_points = LittleEndian::read_i32(&content[36..40]) as usize;
+                points = read_points(&content[40..], num_points);
                 parts = vec![(0, points.len())];
 
             }
             ShapeType::Polyline | ShapeType::Polygon => {
-                bb = BoundingBox::from_bytes(&bytes[12..44]);
-
-                let num_parts = LittleEndian::read_i32(&bytes[44..48]) as usize;
-                let num_points = LittleEndian::read_i32(&bytes[48..52]) as usize;
-                parts = read_parts(&bytes[52..], num_parts, num_points);
-                let points_start = (52 + 4 * num_parts) as usize;
-                points = read_points(&bytes[points_start..], num_points);
-                
+                bb = BoundingBox::from_bytes(&content[4..36]);
+
+                let num_parts = LittleEndian::read_i32(&content[36..40]) as usize;
+                let num_points = LittleEndian::read_i32(&content[40..44]) as usize;
+                parts = read_parts(&content[44..], num_parts, num_points);
+                let points_start = (44 + 4 * num_parts) as usize;
+                points = read_points(&content[points_start..], num_points);
+
+            }
+            ShapeType::PointZ => {
+                let xy = Point::from_bytes(&content[4..20]);
+                let z = LittleEndian::read_f64(&content[20..28]);
+                let m = LittleEndian::read_f64(&content[28..36]);
+                let p = Point { z: Some(z), m: if m <= NO_DATA { None } else { Some(m) }, ..xy };
+                bb = BoundingBox::from_point(p);
+                points = vec![p];
+                parts = vec![(0, 1)];
+            }
+            ShapeType::PointM => {
+                let xy = Point::from_bytes(&content[4..20]);
+                let m = LittleEndian::read_f64(&content[20..28]);
+                let p = Point { m: if m <= NO_DATA { None } else { Some(m) }, ..xy };
+                bb = BoundingBox::from_point(p);
+                points = vec![p];
+                parts = vec![(0, 1)];
+            }
+            ShapeType::MultiPointZ => {
+                bb = BoundingBox::from_bytes(&content[4..36]);
+                let num_points = LittleEndian::read_i32(&content[36..40]) as usize;
+                let mut pts = read_points(&content[40..], num_points);
+
+                let z_array_start = 40 + 16 * num_points + 16; //skip past points, then the [zmin, zmax] range
+                let after_z = z_array_start + 8 * num_points;
+                if content.len() < after_z {
+                    return Err(ShapeError::BadRecordLength { offset: offset });
+                }
+                let z_values = read_f64_array(&content[z_array_start..], num_points);
+                for (p, z) in pts.iter_mut().zip(z_values) {
+                    p.z = Some(z);
+                }
+
+                if content.len() >= after_z + 16 + 8 * num_points {
+                    let m_array_start = after_z + 16; //skip the [mmin, mmax] range
+                    let m_values = read_m_array(&content[m_array_start..], num_points);
+                    for (p, m) in pts.iter_mut().zip(m_values) {
+                        p.m = m;
+                    }
+                }
+                points = pts;
+                parts = vec![(0, points.len())];
+            }
+            ShapeType::MultiPointM => {
+                bb = BoundingBox::from_bytes(&content[4..36]);
+                let num_points = LittleEndian::read_i32(&content[36..40]) as usize;
+                let mut pts = read_points(&content[40..], num_points);
+
+                let after_points = 40 + 16 * num_points;
+                if content.len() >= after_points + 16 + 8 * num_points {
+                    let m_array_start = after_points + 16;
+                    let m_values = read_m_array(&content[m_array_start..], num_points);
+                    for (p, m) in pts.iter_mut().zip(m_values) {
+                        p.m = m;
+                    }
+                }
+                points = pts;
+                parts = vec![(0, points.len())];
+            }
+            ShapeType::PolylineZ | ShapeType::PolygonZ => {
+                bb = BoundingBox::from_bytes(&content[4..36]);
+
+                let num_parts = LittleEndian::read_i32(&content[36..40]) as usize;
+                let num_points = LittleEndian::read_i32(&content[40..44]) as usize;
+                parts = read_parts(&content[44..], num_parts, num_points);
+                let points_start = 44 + 4 * num_parts;
+                let mut pts = read_points(&content[points_start..], num_points);
+
+                let z_array_start = points_start + 16 * num_points + 16;
+                let after_z = z_array_start + 8 * num_points;
+                if content.len() < after_z {
+                    return Err(ShapeError::BadRecordLength { offset: offset });
+                }
+                let z_values = read_f64_array(&content[z_array_start..], num_points);
+                for (p, z) in pts.iter_mut().zip(z_values) {
+                    p.z = Some(z);
+                }
+
+                if content.len() >= after_z + 16 + 8 * num_points {
+                    let m_array_start = after_z + 16;
+                    let m_values = read_m_array(&content[m_array_start..], num_points);
+                    for (p, m) in pts.iter_mut().zip(m_values) {
+                        p.m = m;
+                    }
+                }
+                points = pts;
             }
-            _ => return Err(From::from("shape type not implemented yet"))
+            ShapeType::PolylineM | ShapeType::PolygonM => {
+                bb = BoundingBox::from_bytes(&content[4..36]);
+
+                let num_parts = LittleEndian::read_i32(&content[36..40]) as usize;
+                let num_points = LittleEndian::read_i32(&content[40..44]) as usize;
+                parts = read_parts(&content[44..], num_parts, num_points);
+                let points_start = 44 + 4 * num_parts;
+                let mut pts = read_points(&content[points_start..], num_points);
+
+                let after_points = points_start + 16 * num_points;
+                if content.len() >= after_points + 16 + 8 * num_points {
+                    let m_array_start = after_points + 16;
+                    let m_values = read_m_array(&content[m_array_start..], num_points);
+                    for (p, m) in pts.iter_mut().zip(m_values) {
+                        p.m = m;
+                    }
+                }
+                points = pts;
+            }
+            _ => return Err(ShapeError::UnsupportedShapeType(shape_type))
         }
-        *bytes = bytes.split_at((record_length + 8) as usize).1;
         Ok(Self {
                shape_type: shape_type,
                bounding_box: bb,
@@ -221,9 +402,294 @@ impl Shape {
     }
 }
 
+fn write_i32<W: Write>(w: &mut W, v: i32) -> Result<(), ShapeError> {
+    let mut buf = [0u8; 4];
+    LittleEndian::write_i32(&mut buf, v);
+    w.write_all(&buf)?;
+    Ok(())
+}
+
+fn write_f64<W: Write>(w: &mut W, v: f64) -> Result<(), ShapeError> {
+    let mut buf = [0u8; 8];
+    LittleEndian::write_f64(&mut buf, v);
+    w.write_all(&buf)?;
+    Ok(())
+}
+
+fn write_point<W: Write>(w: &mut W, p: Point) -> Result<(), ShapeError> {
+    write_f64(w, p.x)?;
+    write_f64(w, p.y)
+}
+
+fn write_bbox<W: Write>(w: &mut W, bb: &BoundingBox) -> Result<(), ShapeError> {
+    write_f64(w, bb.min.x)?;
+    write_f64(w, bb.min.y)?;
+    write_f64(w, bb.max.x)?;
+    write_f64(w, bb.max.y)
+}
+
+fn min_max(values: &[f64]) -> (f64, f64) {
+    values.iter().fold((f64::INFINITY, f64::NEG_INFINITY),
+                        |(lo, hi), &v| (lo.min(v), hi.max(v)))
+}
+
+fn write_z_block<W: Write>(w: &mut W, points: &[Point]) -> Result<(), ShapeError> {
+    let z_values: Vec<f64> = points.iter().map(|p| p.z.unwrap_or(0.0)).collect();
+    let (zmin, zmax) = min_max(&z_values);
+    write_f64(w, zmin)?;
+    write_f64(w, zmax)?;
+    for z in &z_values {
+        write_f64(w, *z)?;
+    }
+    Ok(())
+}
+
+//the NoData sentinel stands in for points whose M value wasn't present on read
+fn write_m_block<W: Write>(w: &mut W, points: &[Point]) -> Result<(), ShapeError> {
+    let m_values: Vec<f64> = points.iter().map(|p| p.m.unwrap_or(NO_DATA)).collect();
+    let (mmin, mmax) = min_max(&m_values);
+    write_f64(w, mmin)?;
+    write_f64(w, mmax)?;
+    for m in &m_values {
+        write_f64(w, *m)?;
+    }
+    Ok(())
+}
+
+impl ToWriter for Shape {
+    type Error = ShapeError;
+
+    fn to_writer<W: Write>(&self, w: &mut W, _endian: Endian) -> Result<(), ShapeError> {
+        write_i32(w, self.shape_type as i32)?;
+
+        match self.shape_type {
+            ShapeType::Null => {}
+            ShapeType::Point | ShapeType::PointZ | ShapeType::PointM => {
+                let p = self.points[0];
+                write_point(w, p)?;
+                if self.shape_type == ShapeType::PointZ {
+                    write_f64(w, p.z.unwrap_or(0.0))?;
+                }
+                if self.shape_type == ShapeType::PointZ || self.shape_type == ShapeType::PointM {
+                    write_f64(w, p.m.unwrap_or(NO_DATA))?;
+                }
+            }
+            ShapeType::MultiPoint | ShapeType::MultiPointZ | ShapeType::MultiPointM => {
+                write_bbox(w, &self.bounding_box)?;
+                write_i32(w, self.points.len() as i32)?;
+                for &p in &self.points {
+                    write_point(w, p)?;
+                }
+                if self.shape_type == ShapeType::MultiPointZ {
+                    write_z_block(w, &self.points)?;
+                }
+                if self.shape_type == ShapeType::MultiPointZ || self.shape_type == ShapeType::MultiPointM {
+                    write_m_block(w, &self.points)?;
+                }
+            }
+            ShapeType::Polyline | ShapeType::Polygon | ShapeType::PolylineZ |
+            ShapeType::PolygonZ | ShapeType::PolylineM | ShapeType::PolygonM => {
+                write_bbox(w, &self.bounding_box)?;
+                write_i32(w, self.parts.len() as i32)?;
+                write_i32(w, self.points.len() as i32)?;
+                for &(start, _) in &self.parts {
+                    write_i32(w, start as i32)?;
+                }
+                for &p in &self.points {
+                    write_point(w, p)?;
+                }
+                match self.shape_type {
+                    ShapeType::PolylineZ | ShapeType::PolygonZ => {
+                        write_z_block(w, &self.points)?;
+                        write_m_block(w, &self.points)?;
+                    }
+                    ShapeType::PolylineM | ShapeType::PolygonM => {
+                        write_m_block(w, &self.points)?;
+                    }
+                    _ => {}
+                }
+            }
+            ShapeType::MultiPatch => return Err(ShapeError::UnsupportedShapeType(self.shape_type)),
+        }
+        Ok(())
+    }
+}
+
+impl ToWriter for ShapeFile {
+    type Error = ShapeError;
+
+    fn to_writer<W: Write>(&self, w: &mut W, endian: Endian) -> Result<(), ShapeError> {
+        let mut body = Vec::new();
+        for (i, shape) in self.shapes.iter().enumerate() {
+            let mut content = Vec::new();
+            shape.to_writer(&mut content, endian)?;
+
+            let mut record_header = [0u8; 8];
+            BigEndian::write_i32(&mut record_header[0..4], (i + 1) as i32);
+            BigEndian::write_i32(&mut record_header[4..8], (content.len() / 2) as i32);
+            body.extend_from_slice(&record_header);
+            body.extend_from_slice(&content);
+        }
+
+        let mut header = [0u8; 100];
+        BigEndian::write_u32(&mut header[0..4], 0x0000270a);
+        BigEndian::write_u32(&mut header[24..28], ((100 + body.len()) / 2) as u32);
+        LittleEndian::write_i32(&mut header[28..32], 1000);
+        LittleEndian::write_i32(&mut header[32..36], self.shape_type as i32);
+        LittleEndian::write_f64(&mut header[36..44], self.bounding_box.min.x);
+        LittleEndian::write_f64(&mut header[44..52], self.bounding_box.min.y);
+        LittleEndian::write_f64(&mut header[52..60], self.bounding_box.max.x);
+        LittleEndian::write_f64(&mut header[60..68], self.bounding_box.max.y);
+
+        //same per-point min/max this writer already computes per-record in
+        //write_z_block/write_m_block, just rolled up across every shape
+        let all_points: Vec<Point> = self.shapes.iter().flat_map(|s| s.points.iter().cloned()).collect();
+        let (zmin, zmax, mmin, mmax) = if all_points.is_empty() {
+            (0.0, 0.0, 0.0, 0.0)
+        } else {
+            let z_values: Vec<f64> = all_points.iter().map(|p| p.z.unwrap_or(0.0)).collect();
+            let m_values: Vec<f64> = all_points.iter().map(|p| p.m.unwrap_or(NO_DATA)).collect();
+            let (zmin, zmax) = min_max(&z_values);
+            let (mmin, mmax) = min_max(&m_values);
+            (zmin, zmax, mmin, mmax)
+        };
+        LittleEndian::write_f64(&mut header[68..76], zmin);
+        LittleEndian::write_f64(&mut header[76..84], zmax);
+        LittleEndian::write_f64(&mut header[84..92], mmin);
+        LittleEndian::write_f64(&mut header[92..100], mmax);
+
+        w.write_all(&header)?;
+        w.write_all(&body)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
+
+    //a standalone PolylineZ record (record header + content), hand-built byte
+    //by byte so the z_array_start/m_array_start offset math is exercised the
+    //same way a real .shp file would -- one part, two points, one point's M
+    //value is the NoData sentinel
+    fn polyline_z_record_bytes() -> Vec<u8> {
+        let mut content = Vec::new();
+        content.extend_from_slice(&{
+            let mut b = [0u8; 4];
+            LittleEndian::write_i32(&mut b, ShapeType::PolylineZ as i32);
+            b
+        });
+        for v in &[10.0, 20.0, 30.0, 40.0] {
+            //bounding box xmin, ymin, xmax, ymax
+            let mut b = [0u8; 8];
+            LittleEndian::write_f64(&mut b, *v);
+            content.extend_from_slice(&b);
+        }
+        let mut num_parts = [0u8; 4];
+        LittleEndian::write_i32(&mut num_parts, 1);
+        content.extend_from_slice(&num_parts);
+        let mut num_points = [0u8; 4];
+        LittleEndian::write_i32(&mut num_points, 2);
+        content.extend_from_slice(&num_points);
+        content.extend_from_slice(&[0u8; 4]); //parts[0] start index
+
+        for &(x, y) in &[(10.0, 20.0), (30.0, 40.0)] {
+            let mut b = [0u8; 8];
+            LittleEndian::write_f64(&mut b, x);
+            content.extend_from_slice(&b);
+            LittleEndian::write_f64(&mut b, y);
+            content.extend_from_slice(&b);
+        }
+
+        content.extend_from_slice(&[0u8; 16]); //zmin/zmax, unused on read
+        for z in &[100.0, 200.0] {
+            let mut b = [0u8; 8];
+            LittleEndian::write_f64(&mut b, *z);
+            content.extend_from_slice(&b);
+        }
+
+        content.extend_from_slice(&[0u8; 16]); //mmin/mmax, unused on read
+        for m in &[NO_DATA, 5.5] {
+            let mut b = [0u8; 8];
+            LittleEndian::write_f64(&mut b, *m);
+            content.extend_from_slice(&b);
+        }
+
+        let mut record = Vec::new();
+        let mut record_number = [0u8; 4];
+        BigEndian::write_i32(&mut record_number, 1);
+        record.extend_from_slice(&record_number);
+        let mut content_length_words = [0u8; 4];
+        BigEndian::write_i32(&mut content_length_words, (content.len() / 2) as i32);
+        record.extend_from_slice(&content_length_words);
+        record.extend_from_slice(&content);
+        record
+    }
+
+    #[test]
+    fn polyline_z_decodes_points_z_and_m() {
+        let bytes = polyline_z_record_bytes();
+        let shape = Shape::from_reader(&mut Cursor::new(bytes), Endian::Little).unwrap();
+
+        assert_eq!(shape.shape_type, ShapeType::PolylineZ);
+        assert_eq!(shape.parts, vec![(0, 2)]);
+        assert_eq!(shape.points.len(), 2);
+
+        assert_eq!(shape.points[0].x, 10.0);
+        assert_eq!(shape.points[0].y, 20.0);
+        assert_eq!(shape.points[0].z, Some(100.0));
+        assert_eq!(shape.points[0].m, None); //NoData sentinel decodes to None
+
+        assert_eq!(shape.points[1].x, 30.0);
+        assert_eq!(shape.points[1].y, 40.0);
+        assert_eq!(shape.points[1].z, Some(200.0));
+        assert_eq!(shape.points[1].m, Some(5.5));
+    }
+
+    #[test]
+    fn shape_file_to_writer_round_trip() {
+        let p0 = Point { x: 1.0, y: 2.0, z: Some(3.0), m: Some(4.0) };
+        let p1 = Point { x: 5.0, y: 6.0, z: Some(7.0), m: None };
+        let shape = Shape {
+            shape_type: ShapeType::PolygonZ,
+            bounding_box: BoundingBox::from_bytes(&{
+                let mut b = [0u8; 32];
+                LittleEndian::write_f64(&mut b[0..8], p0.x);
+                LittleEndian::write_f64(&mut b[8..16], p0.y);
+                LittleEndian::write_f64(&mut b[16..24], p1.x);
+                LittleEndian::write_f64(&mut b[24..32], p1.y);
+                b
+            }),
+            points: vec![p0, p1],
+            parts: vec![(0, 2)],
+        };
+        let shapefile = ShapeFile {
+            bounding_box: BoundingBox::from_point(p0),
+            shape_type: ShapeType::PolygonZ,
+            shapes: vec![shape],
+        };
+
+        let mut bytes = Vec::new();
+        shapefile.to_writer(&mut bytes, Endian::Little).unwrap();
+
+        let reparsed = ShapeFile::from_reader(&mut Cursor::new(bytes), Endian::Little).unwrap();
+        assert_eq!(reparsed.shapes.len(), 1);
+        let reparsed_shape = &reparsed.shapes[0];
+        assert_eq!(reparsed_shape.shape_type, ShapeType::PolygonZ);
+        assert_eq!(reparsed_shape.parts, vec![(0, 2)]);
+
+        assert_eq!(reparsed_shape.points[0].x, p0.x);
+        assert_eq!(reparsed_shape.points[0].y, p0.y);
+        assert_eq!(reparsed_shape.points[0].z, p0.z);
+        assert_eq!(reparsed_shape.points[0].m, p0.m);
+
+        assert_eq!(reparsed_shape.points[1].x, p1.x);
+        assert_eq!(reparsed_shape.points[1].y, p1.y);
+        assert_eq!(reparsed_shape.points[1].z, p1.z);
+        assert_eq!(reparsed_shape.points[1].m, p1.m); //None round-trips via the NoData sentinel
+    }
+
     #[test]
     fn shapefile_test() {
         let shapefile = ShapeFile::from_file("test_inputs/states.shp").unwrap();
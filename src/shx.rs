@@ -0,0 +1,206 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder, BigEndian, LittleEndian};
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::error::ShapeError;
+use crate::reader::{Endian, FromReader};
+use crate::shapefile::Shape;
+
+/// One entry of a `.shx` index: where the corresponding record starts in the
+/// `.shp` file, and how long its content is, both already converted from
+/// 16-bit words to bytes.
+#[derive(Debug, Copy, Clone)]
+pub struct ShapeIndexEntry {
+    pub offset: u64,
+    pub content_length: u64,
+}
+
+/// The `.shx` index that sits alongside a `.shp` file: a 100-byte header
+/// identical in shape to the `.shp` header, followed by one 8-byte
+/// (offset, content-length) entry per record.
+pub struct ShapeIndex {
+    entries: Vec<ShapeIndexEntry>,
+}
+
+impl FromReader for ShapeIndex {
+    type Error = ShapeError;
+
+    fn from_reader<R: Read + Seek>(r: &mut R, _endian: Endian) -> Result<Self, ShapeError> {
+        let mut header = [0u8; 100];
+        r.read_exact(&mut header)?;
+
+        let magic = BigEndian::read_u32(&header[..4]);
+        if magic != 0x270a {
+            let mut magic_bytes = [0u8; 4];
+            magic_bytes.copy_from_slice(&header[..4]);
+            return Err(ShapeError::BadMagic(magic_bytes));
+        }
+
+        let file_length = BigEndian::read_u32(&header[24..28]) as u64 * 2;
+        let total_length = r.seek(SeekFrom::End(0))?;
+        if total_length != file_length {
+            return Err(ShapeError::FileLengthMismatch {
+                header: file_length,
+                actual: total_length,
+            });
+        }
+        r.seek(SeekFrom::Start(100))?;
+
+        let mut entries = Vec::new();
+        while r.stream_position()? < total_length {
+            let mut buf = [0u8; 8];
+            r.read_exact(&mut buf)?;
+            entries.push(ShapeIndexEntry {
+                offset: BigEndian::read_i32(&buf[0..4]) as u64 * 2,
+                content_length: BigEndian::read_i32(&buf[4..8]) as u64 * 2,
+            });
+        }
+
+        Ok(ShapeIndex { entries: entries })
+    }
+}
+
+impl ShapeIndex {
+    pub fn from_file(filename: &str) -> Result<Self, ShapeError> {
+        let mut f = File::open(filename)?;
+        Self::from_reader(&mut f, Endian::Big)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entry(&self, index: usize) -> Option<&ShapeIndexEntry> {
+        self.entries.get(index)
+    }
+}
+
+/// A `.shp` file paired with its `.shx` index, giving O(1) random access to
+/// individual shapes instead of walking the whole file front-to-back.
+pub struct IndexedShapeFile<R> {
+    reader: R,
+    index: ShapeIndex,
+}
+
+impl<R: Read + Seek> IndexedShapeFile<R> {
+    pub fn new(reader: R, index: ShapeIndex) -> Self {
+        Self {
+            reader: reader,
+            index: index,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Seek straight to shape `i`'s record and parse only that record,
+    /// without touching any of the others.
+    pub fn shape(&mut self, i: usize) -> Result<Shape, ShapeError> {
+        let entry = self.index.entry(i).ok_or(ShapeError::IndexOutOfRange {
+            index: i,
+            len: self.index.len(),
+        })?;
+        self.reader.seek(SeekFrom::Start(entry.offset))?;
+        Shape::from_reader(&mut self.reader, Endian::Little)
+    }
+}
+
+impl IndexedShapeFile<File> {
+    pub fn from_files(shp_filename: &str, shx_filename: &str) -> Result<Self, ShapeError> {
+        let index = ShapeIndex::from_file(shx_filename)?;
+        let reader = File::open(shp_filename)?;
+        Ok(Self::new(reader, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn main_header(file_length_words: u32) -> [u8; 100] {
+        let mut header = [0u8; 100];
+        BigEndian::write_u32(&mut header[0..4], 0x270a);
+        BigEndian::write_u32(&mut header[24..28], file_length_words);
+        LittleEndian::write_i32(&mut header[28..32], 1000);
+        LittleEndian::write_i32(&mut header[32..36], 1); //Point
+        header
+    }
+
+    //a standalone Point record: 8-byte record header + 20-byte content
+    fn point_record_bytes(record_number: i32, x: f64, y: f64) -> Vec<u8> {
+        let mut record = vec![0u8; 0];
+        let mut record_number_bytes = [0u8; 4];
+        BigEndian::write_i32(&mut record_number_bytes, record_number);
+        record.extend_from_slice(&record_number_bytes);
+        let mut content_length_words = [0u8; 4];
+        BigEndian::write_i32(&mut content_length_words, 10); //20 content bytes / 2
+        record.extend_from_slice(&content_length_words);
+
+        let mut shape_type = [0u8; 4];
+        LittleEndian::write_i32(&mut shape_type, 1); //Point
+        record.extend_from_slice(&shape_type);
+        let mut xy = [0u8; 8];
+        LittleEndian::write_f64(&mut xy, x);
+        record.extend_from_slice(&xy);
+        LittleEndian::write_f64(&mut xy, y);
+        record.extend_from_slice(&xy);
+        record
+    }
+
+    #[test]
+    fn indexed_shape_file_random_access() {
+        let record1 = point_record_bytes(1, 1.0, 2.0);
+        let record2 = point_record_bytes(2, 3.0, 4.0);
+        assert_eq!(record1.len(), 28);
+
+        let mut shp_bytes = Vec::new();
+        let shp_file_length_words = (100 + record1.len() + record2.len()) as u32 / 2;
+        shp_bytes.extend_from_slice(&main_header(shp_file_length_words));
+        let record1_offset = shp_bytes.len() as u64;
+        shp_bytes.extend_from_slice(&record1);
+        let record2_offset = shp_bytes.len() as u64;
+        shp_bytes.extend_from_slice(&record2);
+
+        let mut shx_bytes = Vec::new();
+        let shx_file_length_words = (100 + 2 * 8) as u32 / 2;
+        shx_bytes.extend_from_slice(&main_header(shx_file_length_words));
+        for &offset in &[record1_offset, record2_offset] {
+            let mut entry = [0u8; 8];
+            BigEndian::write_i32(&mut entry[0..4], (offset / 2) as i32);
+            BigEndian::write_i32(&mut entry[4..8], 10); //20 content bytes / 2
+            shx_bytes.extend_from_slice(&entry);
+        }
+
+        let index = ShapeIndex::from_reader(&mut Cursor::new(shx_bytes), Endian::Big).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let mut indexed = IndexedShapeFile::new(Cursor::new(shp_bytes), index);
+
+        //fetch out of order to prove each lookup seeks directly to its own
+        //record instead of relying on the previous lookup's position
+        let second = indexed.shape(1).unwrap();
+        assert_eq!(second.points()[0].x, 3.0);
+        assert_eq!(second.points()[0].y, 4.0);
+
+        let first = indexed.shape(0).unwrap();
+        assert_eq!(first.points()[0].x, 1.0);
+        assert_eq!(first.points()[0].y, 2.0);
+
+        match indexed.shape(2) {
+            Err(ShapeError::IndexOutOfRange { index: 2, len: 2 }) => {}
+            other => panic!("expected IndexOutOfRange, got {:?}", other),
+        }
+    }
+}
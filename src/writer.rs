@@ -0,0 +1,29 @@
+use std::fs::{self, File};
+use std::io::Write;
+
+use crate::reader::Endian;
+
+/// Serialize `Self` to any `Write` sink, the inverse of `FromReader`.
+pub trait ToWriter {
+    type Error: From<::std::io::Error>;
+
+    fn to_writer<W: Write>(&self, w: &mut W, endian: Endian) -> Result<(), Self::Error>;
+
+    /// Write to `path`, but skip the write entirely if `path` already holds
+    /// these exact bytes -- handy for build scripts that shouldn't bump a
+    /// file's mtime (and trigger downstream rebuilds) when nothing changed.
+    fn write_if_changed(&self, path: &str, endian: Endian) -> Result<(), Self::Error> {
+        let mut buffer = Vec::new();
+        self.to_writer(&mut buffer, endian)?;
+
+        if let Ok(existing) = fs::read(path) {
+            if existing == buffer {
+                return Ok(());
+            }
+        }
+
+        let mut f = File::create(path)?;
+        f.write_all(&buffer)?;
+        Ok(())
+    }
+}